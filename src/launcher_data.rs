@@ -47,4 +47,6 @@ pub(crate) struct VersionInfoDownloads {
 #[derive(Deserialize)]
 pub(crate) struct VersionInfoDownload {
     pub(crate) url: Url,
+    pub(crate) sha1: String,
+    pub(crate) size: u64,
 }
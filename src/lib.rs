@@ -11,12 +11,19 @@ use {
             Path,
             PathBuf,
         },
+        process::Stdio,
         str::FromStr,
         time::Duration,
     },
-    futures::stream::TryStreamExt as _,
-    itertools::Itertools as _,
-    serde::Deserialize,
+    futures::stream::{
+        self,
+        Stream,
+        TryStreamExt as _,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
     tokio::{
         io::{
             AsyncBufReadExt as _,
@@ -25,6 +32,7 @@ use {
         process::Command,
     },
     tokio_stream::wrappers::LinesStream,
+    url::Url,
     wheel::{
         fs::{
             self,
@@ -52,10 +60,22 @@ use {
 };
 
 mod launcher_data;
+mod log;
+mod mrpack;
+mod slp;
+mod sources;
 mod util;
 
+pub use crate::{
+    log::LogEvent,
+    slp::ServerStatus,
+    sources::ServerType,
+};
+
 const BASE_DIR: &str = "/opt/wurstmineberg";
 const WORLDS_DIR: &str = "/opt/wurstmineberg/world";
+/// How long `command` waits for a starting server to become ready before retrying the connection.
+const READY_TIMEOUT: Duration = Duration::from_secs(120);
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -63,16 +83,27 @@ pub enum Error {
     #[error(transparent)] Json(#[from] serde_json::Error),
     #[error(transparent)] Rcon(#[from] rcon::Error),
     #[error(transparent)] Reqwest(#[from] reqwest::Error),
+    #[error(transparent)] Url(#[from] url::ParseError),
     #[error(transparent)] Wheel(#[from] wheel::Error),
+    #[error(transparent)] Zip(#[from] zip::result::ZipError),
+    #[error("downloaded file did not match the expected checksum (expected {expected}, got {actual})")]
+    ChecksumMismatch {
+        expected: String,
+        actual: String,
+    },
     #[error("no RCON password is configured for this world")]
     RconDisabled,
+    #[error("modpack declares an unsafe file path that would escape the world directory: {0}")]
+    InsecureModpackPath(String),
     #[error("failed to parse server.properties")]
     ServerPropertiesParse,
+    #[error("updating this server type is not yet supported")]
+    UnsupportedServerType,
     #[error("given version spec does not match any Minecraft version")]
     VersionSpec,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(default, deny_unknown_fields, rename_all = "camelCase")]
 pub struct Config {
     extra_args: Vec<String>,
@@ -81,6 +112,9 @@ pub struct Config {
     #[serde(rename = "memMinMB")]
     mem_min_mb: usize,
     modded: bool,
+    server_type: ServerType,
+    /// The pinned Minecraft version, if any (e.g. set when installing a modpack).
+    version: Option<String>,
 }
 
 impl Config {
@@ -91,6 +125,11 @@ impl Config {
             Config::default()
         })
     }
+
+    pub fn save(&self, path: impl AsRef<Path> + Copy) -> Result<(), Error> {
+        serde_json::to_writer_pretty(std::fs::File::create(path).at(path)?, self)?; //TODO use async_json?
+        Ok(())
+    }
 }
 
 impl Default for Config {
@@ -100,40 +139,108 @@ impl Default for Config {
             mem_max_mb: 1536, // the recommended default for Linode 2GB
             mem_min_mb: 1024, // the recommended default for Linode 2GB
             modded: false,
+            server_type: ServerType::default(),
+            version: None,
         }
     }
 }
 
+/// A single line of a `server.properties` file, preserving comments and blank lines verbatim so
+/// that saving the file back is non-destructive.
+#[derive(Debug)]
+enum PropertyLine {
+    Entry {
+        key: String,
+        value: String,
+    },
+    Other(String),
+}
+
+/// The contents of a `server.properties` file, preserving every key/value pair in order alongside
+/// comments so edits round-trip cleanly. Typed accessors are provided for the commonly used keys.
 #[derive(Debug)]
 pub struct ServerProperties {
-    rcon_password: Option<String>,
-    rcon_port: u16,
+    lines: Vec<PropertyLine>,
 }
 
 impl ServerProperties {
     async fn load(path: impl AsRef<Path> + Copy) -> Result<ServerProperties, Error> {
         let file = BufReader::new(File::open(path).await?);
-        let mut prop = ServerProperties::default();
-        let mut lines = LinesStream::new(file.lines());
-        while let Some(line) = lines.try_next().await.at(path)? {
-            if line.starts_with('#') { continue }
-            let (key, value) = line.splitn(2, '=').collect_tuple().ok_or(Error::ServerPropertiesParse)?;
-            match key {
-                "rcon.password" => { prop.rcon_password = Some(value.to_string()); }
-                "rcon.port" => { prop.rcon_port = value.parse()?; }
-                _ => {} //TODO parse remaining keys, reject invalid keys
+        let mut lines = Vec::default();
+        let mut stream = LinesStream::new(file.lines());
+        while let Some(line) = stream.try_next().await.at(path)? {
+            if line.starts_with('#') || line.trim().is_empty() {
+                lines.push(PropertyLine::Other(line));
+            } else if let Some((key, value)) = line.split_once('=') {
+                lines.push(PropertyLine::Entry { key: key.to_string(), value: value.to_string() });
+            } else {
+                return Err(Error::ServerPropertiesParse)
             }
         }
-        Ok(prop)
+        Ok(ServerProperties { lines })
+    }
+
+    /// Writes the properties back to `path`, preserving comments and ordering.
+    async fn save(&self, path: impl AsRef<Path> + Copy) -> Result<(), Error> {
+        let mut out = String::default();
+        for line in &self.lines {
+            match line {
+                PropertyLine::Entry { key, value } => {
+                    out.push_str(key);
+                    out.push('=');
+                    out.push_str(value);
+                }
+                PropertyLine::Other(raw) => out.push_str(raw),
+            }
+            out.push('\n');
+        }
+        fs::write(path, out).await?;
+        Ok(())
     }
-}
 
-impl Default for ServerProperties {
-    fn default() -> ServerProperties {
-        ServerProperties {
-            rcon_password: None,
-            rcon_port: 22575,
+    /// Returns the raw value of `key`, or `None` if it is not present.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines.iter().find_map(|line| match line {
+            PropertyLine::Entry { key: k, value } if k == key => Some(&**value),
+            _ => None,
+        })
+    }
+
+    /// Sets `key` to `value`, updating the existing entry in place or appending a new one.
+    pub fn set(&mut self, key: &str, value: &str) {
+        for line in &mut self.lines {
+            if let PropertyLine::Entry { key: k, value: v } = line {
+                if k == key {
+                    *v = value.to_string();
+                    return
+                }
+            }
         }
+        self.lines.push(PropertyLine::Entry { key: key.to_string(), value: value.to_string() });
+    }
+
+    pub fn rcon_password(&self) -> Option<&str> {
+        self.get("rcon.password").filter(|value| !value.is_empty())
+    }
+
+    pub fn rcon_port(&self) -> u16 {
+        self.get("rcon.port").and_then(|value| value.parse().ok()).unwrap_or(22575)
+    }
+
+    pub fn server_port(&self) -> u16 {
+        self.get("server-port").and_then(|value| value.parse().ok()).unwrap_or(25565)
+    }
+
+    pub fn query_port(&self) -> u16 {
+        self.get("query.port").and_then(|value| value.parse().ok()).unwrap_or(25565)
+    }
+
+    pub fn enable_rcon(&self) -> bool {
+        self.get("enable-rcon").is_some_and(|value| value == "true")
+    }
+
+    pub fn enable_query(&self) -> bool {
+        self.get("enable-query").is_some_and(|value| value == "true")
     }
 }
 
@@ -183,8 +290,17 @@ impl World {
 
     pub async fn command(&self, cmd: &str) -> Result<String, Error> {
         let prop = self.properties().await?;
-        //TODO wait until world is running
-        let mut conn = rcon::Connection::connect(("localhost", prop.rcon_port), &prop.rcon_password.ok_or(Error::RconDisabled)?).await?;
+        if !prop.enable_rcon() { return Err(Error::RconDisabled) }
+        let password = prop.rcon_password().ok_or(Error::RconDisabled)?.to_owned();
+        let addr = ("localhost", prop.rcon_port());
+        let mut conn = match rcon::Connection::connect(addr, &password).await {
+            Ok(conn) => conn,
+            Err(_) => {
+                // the server is still starting up; wait for it to accept connections, then retry once
+                self.wait_until_ready().await?;
+                rcon::Connection::connect(addr, &password).await?
+            }
+        };
         Ok(conn.cmd(cmd).await?)
     }
 
@@ -212,6 +328,75 @@ impl World {
         ServerProperties::load(&self.dir().join("server.properties")).await
     }
 
+    /// Queries the running server over the Server List Ping protocol for its version, MOTD, and player list.
+    pub async fn status(&self) -> Result<ServerStatus, Error> {
+        let prop = self.properties().await?;
+        slp::ping("localhost", prop.server_port()).await
+    }
+
+    /// Returns the raw value of a `server.properties` key for this world, if present.
+    pub async fn get_property(&self, key: &str) -> Result<Option<String>, Error> {
+        Ok(self.properties().await?.get(key).map(str::to_owned))
+    }
+
+    /// Sets a `server.properties` key for this world, writing the file back non-destructively.
+    pub async fn set_property(&self, key: &str, value: &str) -> Result<(), Error> {
+        let path = self.dir().join("server.properties");
+        let mut prop = ServerProperties::load(&path).await?;
+        prop.set(key, value);
+        prop.save(&path).await?;
+        Ok(())
+    }
+
+    /// Returns a stream of [`LogEvent`]s for this world by following its log.
+    ///
+    /// Uses `journalctl -u minecraft@{world} -f -o cat`, falling back to tailing the latest log
+    /// file if `journalctl` is unavailable.
+    pub async fn watch(&self) -> Result<impl Stream<Item = Result<LogEvent, Error>>, Error> {
+        let mut child = Command::new("journalctl")
+            .arg("-u").arg(format!("minecraft@{self}"))
+            .arg("-f")
+            .arg("-o").arg("cat")
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn();
+        if child.is_err() {
+            child = Command::new("tail")
+                .arg("-n").arg("0")
+                .arg("-F").arg(self.dir().join("logs").join("latest.log"))
+                .stdout(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn();
+        }
+        let mut child = child.at_command("journalctl")?;
+        let stdout = child.stdout.take().expect("child spawned with piped stdout");
+        let lines = BufReader::new(stdout).lines();
+        Ok(stream::unfold((child, lines), |(child, mut lines)| async move {
+            match lines.next_line().await.at_unknown() {
+                Ok(Some(line)) => Some((Ok(log::parse(&line)), (child, lines))),
+                Ok(None) => None,
+                Err(e) => Some((Err(Error::from(e)), (child, lines))),
+            }
+        }))
+    }
+
+    /// Waits for the server to report the `ServerReady` startup marker on its log, giving up after
+    /// `READY_TIMEOUT` so a server that is already past the marker (and thus won't emit it again)
+    /// doesn't block the caller forever — the caller retries the connection once this returns.
+    async fn wait_until_ready(&self) -> Result<(), Error> {
+        let wait = async {
+            let mut events = Box::pin(self.watch().await?);
+            while let Some(event) = events.try_next().await? {
+                if let LogEvent::ServerReady = event { break }
+            }
+            Ok(())
+        };
+        match tokio::time::timeout(READY_TIMEOUT, wait).await {
+            Ok(result) => result,
+            Err(_) => Ok(()),
+        }
+    }
+
     #[cfg(unix)]
     pub fn run(&self) {
         let mut signals = Signals::new(&[SIGTERM]).expect("failed to set up signal handler");
@@ -230,6 +415,9 @@ impl World {
         for arg in config.extra_args {
             java.arg(arg);
         }
+        for arg in config.server_type.extra_args() {
+            java.arg(arg);
+        }
         java.arg("-jar");
         java.arg(self.dir().join("minecraft_server.jar"));
         java.current_dir(self.dir());
@@ -288,22 +476,33 @@ impl World {
         Ok(was_running)
     }
 
-    pub async fn update(&self, target_version: VersionSpec) -> Result<(), Error> {
+    /// Updates the world's server jar. When `target_version` is `None`, falls back to the version
+    /// pinned in the world's config (e.g. set by `install_modpack`), or the latest release if none.
+    pub async fn update(&self, target_version: Option<VersionSpec>) -> Result<(), Error> {
         let client = reqwest::Client::builder()
             .user_agent(concat!("systemd-minecraft/", env!("CARGO_PKG_VERSION")))
             .timeout(Duration::from_secs(30))
             .use_rustls_tls()
             .build()?;
-        let version_manifest = client.get("https://launchermeta.mojang.com/mc/game/version_manifest.json").send().await?.error_for_status()?.json::<launcher_data::VersionManifest>().await?;
-        let version = version_manifest.get(target_version).ok_or(Error::VersionSpec)?;
-        let server_jar_path = Path::new(BASE_DIR).join("jar").join(format!("minecraft_server.{}.jar", version.id));
+        let config = self.config()?;
+        let target_version = target_version
+            .or_else(|| config.version.clone().map(VersionSpec::Exact))
+            .unwrap_or_default();
+        let resolved = sources::resolve(&client, &config.server_type, target_version).await?;
+        let jar_dir = Path::new(BASE_DIR).join("jar");
+        let server_jar_path = jar_dir.join(format!("minecraft_server.{}.jar", resolved.version));
+        // Only verified jars ever appear at `server_jar_path`: download to a temp path, verify, then
+        // rename into place so an interrupted download can never be mistaken for a complete one.
         if !server_jar_path.exists() {
-            let version_info = client.get(version.url.clone()).send().await?.error_for_status()?.json::<launcher_data::VersionInfo>().await?;
+            let download_path = jar_dir.join(format!("minecraft_server.{}.jar.part", resolved.version));
             crate::util::download(
                 &client,
-                version_info.downloads.server.url,
-                &mut File::create(&server_jar_path).await?
+                resolved.url,
+                &download_path,
+                resolved.checksum,
+                resolved.size,
             ).await?;
+            fs::rename(download_path, &server_jar_path).await?;
         }
         //TODO also back up world in parallel, once wurstminebackup is working correctly
         let was_running = self.stop().await?;
@@ -316,6 +515,73 @@ impl World {
         if was_running { self.start().await?; }
         Ok(())
     }
+
+    /// Installs a Modrinth `.mrpack` modpack into this world.
+    ///
+    /// `path_or_url` may be a local filesystem path or an `http(s)` URL. The modpack's
+    /// `dependencies` are recorded in the world's config (setting `server_type` and `version`),
+    /// every server-relevant file is downloaded to its declared path under the world directory
+    /// (verifying the published SHA-1), and the `overrides/`/`server-overrides/` directories are
+    /// unpacked on top of the world folder. Use `update` afterwards to fetch the server jar itself.
+    pub async fn install_modpack(&self, path_or_url: &str) -> Result<(), Error> {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("systemd-minecraft/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(30))
+            .use_rustls_tls()
+            .build()?;
+        // When given a URL, fetch the pack to a temp file we clean up afterwards.
+        let (mrpack_path, downloaded) = match Url::parse(path_or_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {
+                let dest = self.dir().join("modpack.mrpack");
+                util::download(&client, url, &dest, None, None).await?;
+                (dest, true)
+            }
+            _ => (PathBuf::from(path_or_url), false),
+        };
+        let result = self.install_extracted_modpack(&client, &mrpack_path).await;
+        if downloaded {
+            fs::remove_file(&mrpack_path).await?;
+        }
+        result
+    }
+
+    async fn install_extracted_modpack(&self, client: &reqwest::Client, mrpack_path: &Path) -> Result<(), Error> {
+        let world_dir = self.dir();
+        let mrpack_path = mrpack_path.to_path_buf();
+        let index = tokio::task::spawn_blocking(move || mrpack::extract(&mrpack_path, &world_dir)).await.expect("modpack extraction task panicked")?;
+        let server_type = if let Some(loader_version) = index.dependencies.get("fabric-loader") {
+            ServerType::Fabric { loader_version: Some(loader_version.clone()) }
+        } else if index.dependencies.contains_key("quilt-loader") {
+            ServerType::Quilt
+        } else if index.dependencies.contains_key("forge") || index.dependencies.contains_key("neoforge") {
+            ServerType::Forge
+        } else {
+            ServerType::Vanilla
+        };
+        // Refuse loaders `update` can't later resolve rather than writing an un-updatable config.
+        if matches!(server_type, ServerType::Quilt | ServerType::Forge) {
+            return Err(Error::UnsupportedServerType)
+        }
+        // Download and verify every server-relevant file before committing the new config, so a
+        // mid-loop failure doesn't leave the world reconfigured but only partially populated.
+        for file in &index.files {
+            if !file.is_server_relevant() { continue }
+            let url = file.downloads.first().ok_or(Error::VersionSpec)?.clone();
+            let target = self.dir().join(file.safe_path().ok_or_else(|| Error::InsecureModpackPath(file.path.clone()))?);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            util::download(client, url, &target, Some(util::Checksum::Sha1(file.hashes.sha1.clone())), None).await?;
+        }
+        let mut config = self.config()?;
+        if let Some(version) = index.dependencies.get("minecraft") {
+            config.version = Some(version.clone());
+        }
+        config.server_type = server_type;
+        config.modded = !matches!(config.server_type, ServerType::Vanilla);
+        config.save(&self.dir().join("systemd-minecraft.json"))?;
+        Ok(())
+    }
 }
 
 impl Default for World {
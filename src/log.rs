@@ -0,0 +1,36 @@
+/// A parsed line from a running server's log.
+#[derive(Debug, Clone)]
+pub enum LogEvent {
+    PlayerJoined(String),
+    PlayerLeft(String),
+    Chat {
+        player: String,
+        message: String,
+    },
+    /// The `Done (Xs)! For help` startup marker, emitted once the server is accepting connections.
+    ServerReady,
+    /// Any other log line, kept verbatim.
+    Other(String),
+}
+
+/// Parses a single vanilla log line into a [`LogEvent`], stripping the `[HH:MM:SS] [thread/LEVEL]: `
+/// prefix that precedes the message content.
+pub(crate) fn parse(line: &str) -> LogEvent {
+    let content = line.split_once("]: ").map_or(line, |(_, rest)| rest).trim();
+    if let Some(name) = content.strip_suffix(" joined the game") {
+        LogEvent::PlayerJoined(name.to_string())
+    } else if let Some(name) = content.strip_suffix(" left the game") {
+        LogEvent::PlayerLeft(name.to_string())
+    } else if let Some(chat) = content.strip_prefix('<') {
+        match chat.split_once("> ") {
+            Some((player, message)) => LogEvent::Chat { player: player.to_string(), message: message.to_string() },
+            None => LogEvent::Other(line.to_string()),
+        }
+    } else if let Some(name) = content.strip_suffix(" joined") {
+        LogEvent::PlayerJoined(name.to_string())
+    } else if content.starts_with("Done (") && content.contains("! For help") {
+        LogEvent::ServerReady
+    } else {
+        LogEvent::Other(line.to_string())
+    }
+}
@@ -1,10 +1,14 @@
 #![deny(rust_2018_idioms, unused, unused_import_braces, unused_qualifications, warnings)]
 #![forbid(unsafe_code)]
 
-use systemd_minecraft::{
-    Error,
-    VersionSpec,
-    World,
+use {
+    futures::stream::TryStreamExt as _,
+    systemd_minecraft::{
+        Error,
+        LogEvent,
+        VersionSpec,
+        World,
+    },
 };
 
 #[derive(clap::Parser)]
@@ -19,6 +23,25 @@ enum Args {
     Run {
         world: World,
     },
+    /// Installs a Modrinth `.mrpack` modpack (from a path or URL) into a world.
+    InstallModpack {
+        world: World,
+        path_or_url: String,
+    },
+    /// Gets or sets a key in a world's server.properties. Omit the value to read the current one.
+    Property {
+        world: World,
+        key: String,
+        value: Option<String>,
+    },
+    /// Reports the status (version, MOTD, and online players) of a running world.
+    Status {
+        world: World,
+    },
+    /// Follows a world's log, printing parsed events (joins, leaves, chat) until interrupted.
+    Watch {
+        world: World,
+    },
     /// Updates Minecraft for a world.
     Update {
         world: World,
@@ -38,13 +61,46 @@ async fn main(args: Args) -> Result<(), Error> {
         Args::Run { world } => {
             world.run();
         }
+        Args::InstallModpack { world, path_or_url } => {
+            world.install_modpack(&path_or_url).await?;
+        }
+        Args::Property { world, key, value } => {
+            if let Some(value) = value {
+                world.set_property(&key, &value).await?;
+            } else {
+                match world.get_property(&key).await? {
+                    Some(value) => println!("{value}"),
+                    None => println!("(unset)"),
+                }
+            }
+        }
+        Args::Status { world } => {
+            let status = world.status().await?;
+            println!("{} ({}/{} players online)", status.version.name, status.players.online, status.players.max);
+            println!("{}", status.motd());
+            for player in &status.players.sample {
+                println!("- {}", player.name);
+            }
+        }
+        Args::Watch { world } => {
+            let mut events = std::pin::pin!(world.watch().await?);
+            while let Some(event) = events.try_next().await? {
+                match event {
+                    LogEvent::PlayerJoined(name) => println!("+ {name}"),
+                    LogEvent::PlayerLeft(name) => println!("- {name}"),
+                    LogEvent::Chat { player, message } => println!("<{player}> {message}"),
+                    LogEvent::ServerReady => println!("server ready"),
+                    LogEvent::Other(line) => println!("  {line}"),
+                }
+            }
+        }
         Args::Update { world, version, snapshot } => {
             let target_version = if let Some(version) = version {
-                VersionSpec::Exact(version)
+                Some(VersionSpec::Exact(version))
             } else if snapshot {
-                VersionSpec::LatestSnapshot
+                Some(VersionSpec::LatestSnapshot)
             } else {
-                VersionSpec::LatestRelease
+                None
             };
             world.update(target_version).await?;
         }
@@ -0,0 +1,109 @@
+use {
+    std::{
+        collections::HashMap,
+        io::Read as _,
+        path::{
+            Component,
+            Path,
+            PathBuf,
+        },
+    },
+    serde::Deserialize,
+    url::Url,
+    wheel::traits::IoResultExt as _,
+    crate::Error,
+};
+
+/// The `modrinth.index.json` manifest at the root of a `.mrpack` archive.
+///
+/// See <https://support.modrinth.com/en/articles/8802351-modrinth-modpack-format-mrpack>.
+#[derive(Deserialize)]
+pub(crate) struct ModpackIndex {
+    /// Maps dependency names (`minecraft`, `fabric-loader`, `quilt-loader`, `forge`, …) to versions.
+    pub(crate) dependencies: HashMap<String, String>,
+    pub(crate) files: Vec<ModpackFile>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ModpackFile {
+    pub(crate) path: String,
+    pub(crate) downloads: Vec<Url>,
+    pub(crate) hashes: ModpackHashes,
+    #[serde(default)]
+    pub(crate) env: Option<ModpackEnv>,
+}
+
+impl ModpackFile {
+    /// Whether this file needs to be present on the server. Files marked client-only are skipped.
+    pub(crate) fn is_server_relevant(&self) -> bool {
+        self.env.as_ref().map_or(true, |env| env.server != EnvRequirement::Unsupported)
+    }
+
+    /// The declared `path` as a relative path safe to join onto the world directory, or `None` if
+    /// it is absolute or contains a `..` component and would therefore escape the world directory.
+    pub(crate) fn safe_path(&self) -> Option<PathBuf> {
+        let path = Path::new(&self.path);
+        if path.is_absolute() || path.components().any(|component| component == Component::ParentDir) {
+            None
+        } else {
+            Some(path.to_path_buf())
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ModpackHashes {
+    pub(crate) sha1: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ModpackEnv {
+    #[allow(dead_code)] // deserialized for completeness; only `server` affects what we install
+    client: EnvRequirement,
+    server: EnvRequirement,
+}
+
+#[derive(Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum EnvRequirement {
+    Required,
+    Optional,
+    Unsupported,
+}
+
+/// Reads the index from a `.mrpack` archive and unpacks its `overrides/` and `server-overrides/`
+/// directories on top of `world_dir`, returning the parsed index for the caller to act on.
+///
+/// This is synchronous (the `zip` crate is not async) and is meant to be run on a blocking thread.
+pub(crate) fn extract(mrpack: &Path, world_dir: &Path) -> Result<ModpackIndex, Error> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(mrpack).at(mrpack)?).map_err(Error::Zip)?;
+    let index = {
+        let mut entry = archive.by_name("modrinth.index.json").map_err(Error::Zip)?;
+        let mut buf = String::default();
+        entry.read_to_string(&mut buf).at(mrpack)?;
+        serde_json::from_str::<ModpackIndex>(&buf)?
+    };
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(Error::Zip)?;
+        let Some(name) = entry.enclosed_name().and_then(|name| name.to_str().map(str::to_owned)) else { continue };
+        let rel = if let Some(rel) = name.strip_prefix("overrides/") {
+            rel
+        } else if let Some(rel) = name.strip_prefix("server-overrides/") {
+            rel
+        } else {
+            continue
+        };
+        if rel.is_empty() { continue }
+        let target = world_dir.join(rel);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&target).at(&target)?;
+        } else {
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent).at(parent)?;
+            }
+            let mut out = std::fs::File::create(&target).at(&target)?;
+            std::io::copy(&mut entry, &mut out).at(&target)?;
+        }
+    }
+    Ok(index)
+}
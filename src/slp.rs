@@ -0,0 +1,116 @@
+use {
+    serde::Deserialize,
+    tokio::{
+        io::{
+            AsyncReadExt as _,
+            AsyncWriteExt as _,
+        },
+        net::TcpStream,
+    },
+    wheel::traits::IoResultExt as _,
+    crate::Error,
+};
+
+/// The parsed response to a Server List Ping status request.
+///
+/// See <https://wiki.vg/Server_List_Ping> for the wire format.
+#[derive(Debug, Deserialize)]
+pub struct ServerStatus {
+    pub version: StatusVersion,
+    pub players: StatusPlayers,
+    /// The MOTD, which may be a plain string or a chat component object.
+    pub description: serde_json::Value,
+}
+
+impl ServerStatus {
+    /// The MOTD flattened to plain text, resolving the chat-component form (`text` plus nested
+    /// `extra`) as well as the legacy plain-string form.
+    pub fn motd(&self) -> String {
+        fn flatten(value: &serde_json::Value) -> String {
+            match value {
+                serde_json::Value::String(text) => text.clone(),
+                serde_json::Value::Object(map) => {
+                    let mut text = map.get("text").and_then(serde_json::Value::as_str).unwrap_or_default().to_string();
+                    if let Some(serde_json::Value::Array(extra)) = map.get("extra") {
+                        for child in extra {
+                            text.push_str(&flatten(child));
+                        }
+                    }
+                    text
+                }
+                _ => String::default(),
+            }
+        }
+        flatten(&self.description)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatusVersion {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatusPlayers {
+    pub online: u32,
+    pub max: u32,
+    #[serde(default)]
+    pub sample: Vec<StatusPlayerSample>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatusPlayerSample {
+    pub name: String,
+    pub id: String,
+}
+
+fn write_varint(buf: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        let mut byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value != 0 { byte |= 0x80; }
+        buf.push(byte);
+        if value == 0 { break }
+    }
+}
+
+/// Writes a packet as a VarInt length prefix followed by its body.
+async fn write_packet(stream: &mut TcpStream, body: &[u8]) -> Result<(), Error> {
+    let mut framed = Vec::default();
+    write_varint(&mut framed, body.len() as i32);
+    framed.extend_from_slice(body);
+    stream.write_all(&framed).await.at_unknown()?;
+    Ok(())
+}
+
+async fn read_varint(stream: &mut TcpStream) -> Result<i32, Error> {
+    let mut result = 0u32;
+    for shift in (0..5).map(|i| i * 7) {
+        let mut byte = [0u8; 1];
+        stream.read_exact(&mut byte).await.at_unknown()?;
+        result |= ((byte[0] & 0x7f) as u32) << shift;
+        if byte[0] & 0x80 == 0 { break }
+    }
+    Ok(result as i32)
+}
+
+/// Performs a Server List Ping against `host`:`port` and returns the parsed status JSON.
+pub(crate) async fn ping(host: &str, port: u16) -> Result<ServerStatus, Error> {
+    let mut stream = TcpStream::connect((host, port)).await.at_unknown()?;
+    let mut handshake = Vec::default();
+    write_varint(&mut handshake, 0x00); // packet id
+    write_varint(&mut handshake, -1); // protocol version (unset, we only want the status)
+    write_varint(&mut handshake, host.len() as i32);
+    handshake.extend_from_slice(host.as_bytes());
+    handshake.extend_from_slice(&port.to_be_bytes());
+    write_varint(&mut handshake, 1); // next state: status
+    write_packet(&mut stream, &handshake).await?;
+    write_packet(&mut stream, &[0x00]).await?; // empty status request
+    let _ = read_varint(&mut stream).await?; // total packet length
+    let _ = read_varint(&mut stream).await?; // packet id (0x00)
+    let json_len = read_varint(&mut stream).await? as usize;
+    let mut json = vec![0u8; json_len];
+    stream.read_exact(&mut json).await.at_unknown()?;
+    Ok(serde_json::from_slice(&json)?)
+}
@@ -0,0 +1,147 @@
+use {
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    url::Url,
+    crate::{
+        Error,
+        VersionSpec,
+        launcher_data::VersionManifest,
+        util::Checksum,
+    },
+};
+
+/// A server jar resolved from a source, ready to be downloaded by `update`.
+pub(crate) struct ResolvedServer {
+    /// An identifier for this jar, used to name the cached jar: the game version for vanilla, and
+    /// the game version prefixed with the server type plus the build or loader version otherwise.
+    pub(crate) version: String,
+    pub(crate) url: Url,
+    /// The expected content hash, if the source publishes one. Fabric does not, so its jars are
+    /// downloaded unverified.
+    pub(crate) checksum: Option<Checksum>,
+    pub(crate) size: Option<u64>,
+}
+
+/// The kind of server software a world runs. Vanilla jars come straight from Mojang; Paper and
+/// Fabric resolve their download through a third-party distribution API. Forge and Quilt are not
+/// resolved: `update` fails with `Error::UnsupportedServerType` for them.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ServerType {
+    Vanilla,
+    Paper,
+    Fabric {
+        #[serde(default)]
+        loader_version: Option<String>,
+    },
+    Forge,
+    Quilt,
+}
+
+impl ServerType {
+    /// Extra program arguments this server type needs on the `java` command line, appended by `run`.
+    pub(crate) fn extra_args(&self) -> Vec<String> {
+        Vec::default() //TODO Forge's modern installers write an `@libraries/.../unix_args.txt` that must be passed here
+    }
+}
+
+impl Default for ServerType {
+    fn default() -> ServerType {
+        ServerType::Vanilla
+    }
+}
+
+/// Resolves `spec` against the configured `server_type` into a concrete download.
+pub(crate) async fn resolve(client: &reqwest::Client, server_type: &ServerType, spec: VersionSpec) -> Result<ResolvedServer, Error> {
+    match server_type {
+        ServerType::Vanilla => vanilla(client, spec).await,
+        ServerType::Paper => paper(client, spec).await,
+        ServerType::Fabric { loader_version } => fabric(client, spec, loader_version.clone()).await,
+        ServerType::Forge | ServerType::Quilt => Err(Error::UnsupportedServerType),
+    }
+}
+
+async fn vanilla(client: &reqwest::Client, spec: VersionSpec) -> Result<ResolvedServer, Error> {
+    let version_manifest = client.get("https://launchermeta.mojang.com/mc/game/version_manifest.json").send().await?.error_for_status()?.json::<VersionManifest>().await?;
+    let version = version_manifest.get(spec).ok_or(Error::VersionSpec)?;
+    let version_info = client.get(version.url.clone()).send().await?.error_for_status()?.json::<crate::launcher_data::VersionInfo>().await?;
+    let download = version_info.downloads.server;
+    Ok(ResolvedServer {
+        version: version.id.clone(),
+        url: download.url,
+        checksum: Some(Checksum::Sha1(download.sha1)),
+        size: Some(download.size),
+    })
+}
+
+/// The Minecraft game version a spec refers to, resolving `latest` specs through Mojang's manifest.
+async fn game_version(client: &reqwest::Client, spec: VersionSpec) -> Result<String, Error> {
+    match spec {
+        VersionSpec::Exact(version) => Ok(version),
+        spec => {
+            let version_manifest = client.get("https://launchermeta.mojang.com/mc/game/version_manifest.json").send().await?.error_for_status()?.json::<VersionManifest>().await?;
+            Ok(version_manifest.get(spec).ok_or(Error::VersionSpec)?.id.clone())
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PaperBuilds {
+    builds: Vec<PaperBuild>,
+}
+
+#[derive(Deserialize)]
+struct PaperBuild {
+    build: u32,
+    downloads: PaperDownloads,
+}
+
+#[derive(Deserialize)]
+struct PaperDownloads {
+    application: PaperDownload,
+}
+
+#[derive(Deserialize)]
+struct PaperDownload {
+    name: String,
+    sha256: String,
+}
+
+async fn paper(client: &reqwest::Client, spec: VersionSpec) -> Result<ResolvedServer, Error> {
+    let mc = game_version(client, spec).await?;
+    let builds = client.get(format!("https://api.papermc.io/v2/projects/paper/versions/{mc}/builds"))
+        .send().await?.error_for_status()?.json::<PaperBuilds>().await?;
+    let build = builds.builds.into_iter().last().ok_or(Error::VersionSpec)?;
+    let application = build.downloads.application;
+    let url = format!("https://api.papermc.io/v2/projects/paper/versions/{mc}/builds/{}/downloads/{}", build.build, application.name).parse()?;
+    Ok(ResolvedServer { version: format!("paper-{mc}-{}", build.build), url, checksum: Some(Checksum::Sha256(application.sha256)), size: None })
+}
+
+#[derive(Deserialize)]
+struct FabricVersion {
+    version: String,
+    stable: bool,
+}
+
+async fn latest_stable(client: &reqwest::Client, endpoint: &str) -> Result<String, Error> {
+    let versions = client.get(endpoint).send().await?.error_for_status()?.json::<Vec<FabricVersion>>().await?;
+    versions.iter().find(|version| version.stable)
+        .or_else(|| versions.first())
+        .map(|version| version.version.clone())
+        .ok_or(Error::VersionSpec)
+}
+
+async fn fabric(client: &reqwest::Client, spec: VersionSpec, loader_version: Option<String>) -> Result<ResolvedServer, Error> {
+    let mc = game_version(client, spec).await?;
+    let loader = match loader_version {
+        Some(loader) => loader,
+        None => latest_stable(client, "https://meta.fabricmc.net/v2/versions/loader").await?,
+    };
+    let installer = latest_stable(client, "https://meta.fabricmc.net/v2/versions/installer").await?;
+    // Fabric's meta API does not publish a checksum for the generated server launcher, so this jar
+    // is downloaded without integrity verification.
+    let url = format!("https://meta.fabricmc.net/v2/versions/loader/{mc}/{loader}/{installer}/server/jar").parse()?;
+    Ok(ResolvedServer { version: format!("fabric-{mc}-{loader}"), url, checksum: None, size: None })
+}
@@ -1,34 +1,145 @@
 use {
+    std::{
+        path::Path,
+        time::Duration,
+    },
     futures::stream::TryStreamExt as _,
-    tokio::io::{
-        self,
-        AsyncWrite,
+    sha1::{
+        Digest as _,
+        Sha1,
+    },
+    sha2::Sha256,
+    tokio::{
+        io::AsyncWriteExt as _,
+        time::sleep,
     },
-    tokio_util::compat::FuturesAsyncReadCompatExt as _,
     url::Url,
-    wheel::traits::IoResultExt as _,
+    wheel::{
+        fs::{
+            self,
+            File,
+        },
+        traits::IoResultExt as _,
+    },
     crate::Error,
 };
 
-pub(crate) async fn download(client: &reqwest::Client, url: Url, file: &mut (impl AsyncWrite + Unpin)) -> Result<(), Error> {
-    let mut reader = client.get(url)
+/// Number of download attempts before giving up, including the first.
+const MAX_ATTEMPTS: u32 = 5;
+/// Emit a progress line roughly every time this many bytes have been written.
+const PROGRESS_INTERVAL: u64 = 32 * 1024 * 1024;
+
+/// An expected content hash for a download, tagged with the algorithm that produced it. Mojang and
+/// Modrinth publish SHA-1 digests, PaperMC publishes SHA-256.
+pub(crate) enum Checksum {
+    Sha1(String),
+    Sha256(String),
+}
+
+impl Checksum {
+    fn hex(&self) -> &str {
+        match self {
+            Checksum::Sha1(hex) | Checksum::Sha256(hex) => hex,
+        }
+    }
+}
+
+/// A running digest over the bytes of a download, matching the algorithm of the expected checksum.
+enum Hasher {
+    Sha1(Sha1),
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    fn new(checksum: Option<&Checksum>) -> Hasher {
+        match checksum {
+            Some(Checksum::Sha256(_)) => Hasher::Sha256(Sha256::new()),
+            _ => Hasher::Sha1(Sha1::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::Sha256(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finalize_hex(self) -> String {
+        match self {
+            Hasher::Sha1(hasher) => hex::encode(hasher.finalize()),
+            Hasher::Sha256(hasher) => hex::encode(hasher.finalize()),
+        }
+    }
+}
+
+/// Downloads `url` to `path`, verifying the result against `expected`/`total` when given.
+///
+/// Transient failures (timeouts, connection resets, 5xx responses) are retried with exponential
+/// backoff, restarting the stream from scratch each attempt. `total`, when known, is used to emit
+/// periodic progress so long downloads aren't silent. On a checksum or size mismatch the partial
+/// file is removed and [`Error::ChecksumMismatch`] is returned; the final attempt's error is
+/// otherwise preserved. The caller is expected to download to a temporary path and only promote it
+/// to its final location once this returns `Ok`.
+pub(crate) async fn download(client: &reqwest::Client, url: Url, path: &Path, expected: Option<Checksum>, total: Option<u64>) -> Result<(), Error> {
+    let mut attempt = 0;
+    let (digest, size) = loop {
+        attempt += 1;
+        match try_download(client, url.clone(), path, expected.as_ref(), total).await {
+            Ok(result) => break result,
+            Err(e) => {
+                let retryable = matches!(&e, Error::Reqwest(re) if is_retryable(re));
+                if retryable && attempt < MAX_ATTEMPTS {
+                    let backoff = Duration::from_secs(1 << (attempt - 1));
+                    eprintln!("download attempt {attempt} failed ({e}), retrying in {}s", backoff.as_secs());
+                    sleep(backoff).await;
+                    continue
+                }
+                return Err(e)
+            }
+        }
+    };
+    if let Some(expected) = expected {
+        if !digest.eq_ignore_ascii_case(expected.hex()) {
+            fs::remove_file(path).await?;
+            return Err(Error::ChecksumMismatch { expected: expected.hex().to_string(), actual: digest })
+        }
+    }
+    if let Some(total) = total {
+        if size != total {
+            fs::remove_file(path).await?;
+            return Err(Error::ChecksumMismatch { expected: total.to_string(), actual: size.to_string() })
+        }
+    }
+    Ok(())
+}
+
+/// Streams the body once into `path`, returning the computed digest (in the algorithm of `expected`,
+/// defaulting to SHA-1) and the total number of bytes written.
+async fn try_download(client: &reqwest::Client, url: Url, path: &Path, expected: Option<&Checksum>, total: Option<u64>) -> Result<(String, u64), Error> {
+    let mut file = File::create(path).await?;
+    let mut stream = client.get(url)
         .send().await?
         .error_for_status()?
-        .bytes_stream()
-        //.map_ok(|| )
-        .map_err(reqwest_error_to_io)
-        .into_async_read()
-        .compat();
-    tokio::io::copy(
-        &mut reader,
-        file,
-    ).await.at_unknown()?; //TODO annotate?
-    Ok(())
+        .bytes_stream();
+    let mut hasher = Hasher::new(expected);
+    let mut size = 0;
+    let mut last_report = 0;
+    while let Some(chunk) = stream.try_next().await? {
+        hasher.update(&chunk);
+        size += chunk.len() as u64;
+        file.write_all(&chunk).await.at_unknown()?; //TODO annotate?
+        if size - last_report >= PROGRESS_INTERVAL {
+            last_report = size;
+            match total {
+                Some(total) => eprintln!("downloaded {size}/{total} bytes"),
+                None => eprintln!("downloaded {size} bytes"),
+            }
+        }
+    }
+    Ok((hasher.finalize_hex(), size))
 }
 
-fn reqwest_error_to_io(e: reqwest::Error) -> io::Error {
-    io::Error::new(
-        if e.is_timeout() { io::ErrorKind::TimedOut } else { io::ErrorKind::Other }, //TODO other error kinds depending on methods/status?
-        Box::new(e)
-    )
+fn is_retryable(e: &reqwest::Error) -> bool {
+    e.is_timeout() || e.is_connect() || e.is_request() || e.is_body() || e.status().map_or(false, |status| status.is_server_error())
 }